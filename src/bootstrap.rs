@@ -10,7 +10,8 @@ use axum::{
 };
 use axum_extra::extract::cookie::Key;
 use getset::Getters;
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use rand::Rng;
+use sqlx::{migrate::Migrator, postgres::PgPoolOptions, Error as SqlxError, Executor, PgPool, Row};
 use tokio::{net::TcpListener, signal};
 use tower::{buffer::BufferLayer, limit::RateLimitLayer, ServiceBuilder};
 use tower_http::{cors::CorsLayer, timeout::TimeoutLayer, trace::TraceLayer};
@@ -22,14 +23,22 @@ use crate::{
         refresh_session_by_body, refresh_session_by_cookie, register, revoke_all_sessions,
         revoke_my_session, revoke_user_session, update_me, update_user,
     },
-    utils::{AppConfig, AppResult, DatabaseConfig},
+    utils::{AppConfig, AppResult, DatabaseConfig, TargetSessionAttrs},
 };
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 pub async fn run_application(config: AppConfig) -> AppResult<()> {
     init_tracing()?;
 
     let db_pool = create_connection_pool(config.database()).await?;
 
+    if config.database().get_run_migrations() {
+        run_migrations(&db_pool).await?;
+    } else {
+        tracing::info!("Skipping database migrations (DATABASE_RUN_MIGRATIONS=false)");
+    }
+
     let app = create_router(db_pool, config.clone());
 
     let address = SocketAddr::new(config.server().host().parse()?, *config.server().port());
@@ -63,14 +72,107 @@ fn init_tracing() -> AppResult<()> {
         .context("Failed to initialize tracing")
 }
 
+async fn run_migrations(db_pool: &PgPool) -> AppResult<()> {
+    MIGRATOR
+        .run(db_pool)
+        .await
+        .context("Failed to run database migrations")?;
+
+    // Query the tracking table rather than `MIGRATOR.iter()`, which lists every
+    // embedded migration regardless of whether the database records it as applied.
+    let applied_versions: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(db_pool)
+            .await
+            .context("Failed to read applied database migrations")?;
+    tracing::info!(?applied_versions, "Database migrations up to date");
+
+    Ok(())
+}
+
 pub async fn create_connection_pool(config: &DatabaseConfig) -> AppResult<PgPool> {
-    PgPoolOptions::new()
+    tracing::info!(
+        max_connections = *config.get_max_connections(),
+        "Resolved database connection pool size"
+    );
+
+    let pool_options = PgPoolOptions::new()
         .max_connections(*config.get_max_connections())
         .min_connections(*config.get_min_connections())
         .acquire_timeout(Duration::from_secs(*config.get_acquire_timeout_secs()))
-        .connect_with(config.to_pg_connect_options())
-        .await
-        .context("Failed to create database connection pool")
+        .idle_timeout(Duration::from_secs(config.get_idle_timeout_secs()))
+        .max_lifetime(Duration::from_secs(config.get_max_lifetime_secs()));
+
+    // sqlx's PgConnectOptions has no client-side equivalent of libpq's
+    // `target_session_attrs`, so a standby is rejected after connecting
+    // instead, by checking `pg_is_in_recovery()` on every new connection.
+    let pool_options = match config.get_target_session_attrs() {
+        TargetSessionAttrs::Any => pool_options,
+        TargetSessionAttrs::ReadWrite => pool_options.after_connect(|conn, _meta| {
+            Box::pin(async move {
+                let in_recovery: bool = conn
+                    .fetch_one("SELECT pg_is_in_recovery()")
+                    .await?
+                    .try_get(0)?;
+
+                if in_recovery {
+                    return Err(SqlxError::Configuration(
+                        "target_session_attrs=read-write but connected to a read-only standby"
+                            .into(),
+                    ));
+                }
+
+                Ok(())
+            })
+        }),
+    };
+
+    let connect_options = config.to_pg_connect_options();
+    let max_retries = config.get_connect_max_retries();
+    let base_delay = Duration::from_millis(config.get_connect_retry_base_delay_ms());
+    const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+    for attempt in 0..=max_retries {
+        match pool_options
+            .clone()
+            .connect_with(connect_options.clone())
+            .await
+        {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < max_retries && is_retryable(&e) => {
+                // Cap the exponent itself (not just the resulting delay) so an
+                // operator-configured retry count can't overflow the `1u32 <<` shift.
+                const MAX_EXPONENT: u32 = 16;
+                let delay = base_delay
+                    .checked_mul(1u32 << attempt.min(MAX_EXPONENT))
+                    .unwrap_or(MAX_RETRY_DELAY)
+                    .min(MAX_RETRY_DELAY);
+                let jitter = rand::thread_rng().gen_range(0.0..1.0);
+                let delay = delay.mul_f64(0.5 + 0.5 * jitter);
+                tracing::warn!(
+                    attempt = attempt + 1,
+                    max_retries,
+                    delay_ms = delay.as_millis() as u64,
+                    "Failed to connect to database, retrying: {}",
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e).context("Failed to create database connection pool"),
+        }
+    }
+
+    unreachable!("retry loop always returns before exhausting its range")
+}
+
+/// Whether a connection error is transient and worth retrying, as opposed to
+/// an authentication or configuration error that will never succeed on retry.
+///
+/// TLS failures are excluded: with a private CA / client cert configured,
+/// they almost always mean a bad `ssl_root_cert`/`ssl_client_cert` rather
+/// than a transient network blip, so they should fail fast like auth errors.
+fn is_retryable(error: &SqlxError) -> bool {
+    matches!(error, SqlxError::Io(_))
 }
 
 #[derive(Debug, Clone, Getters)]
@@ -190,4 +292,4 @@ async fn shutdown_signal() {
         _ = ctrl_c => {},
         _ = terminate => {},
     }
-}
\ No newline at end of file
+}
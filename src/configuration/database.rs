@@ -1,4 +1,4 @@
-use std::{process, str::FromStr};
+use std::{path::PathBuf, process, str::FromStr};
 
 use super::{read_env_var, ConfigBuilder, ConfigError};
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
@@ -12,6 +12,20 @@ pub struct DatabaseConfig {
     host: String,
     name: String,
     ssl_mode: PgSslMode,
+    run_migrations: bool,
+    connect_max_retries: u32,
+    connect_retry_base_delay_ms: u64,
+    socket_path: Option<String>,
+    idle_timeout_secs: u64,
+    max_lifetime_secs: u64,
+    target_session_attrs: TargetSessionAttrs,
+    max_connections: u32,
+    min_connections: u32,
+    acquire_timeout_secs: u64,
+    pool_size_cpu_multiplier: u32,
+    ssl_root_cert: Option<PathBuf>,
+    ssl_client_cert: Option<PathBuf>,
+    ssl_client_key: Option<PathBuf>,
 }
 
 impl Default for DatabaseConfig {
@@ -23,6 +37,20 @@ impl Default for DatabaseConfig {
             host: String::from("127.0.0.1"),
             name: String::new(),
             ssl_mode: PgSslMode::default(),
+            run_migrations: true,
+            connect_max_retries: 5,
+            connect_retry_base_delay_ms: 200,
+            socket_path: None,
+            idle_timeout_secs: 600,
+            max_lifetime_secs: 1800,
+            target_session_attrs: TargetSessionAttrs::default(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            pool_size_cpu_multiplier: 4,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
         }
     }
 }
@@ -52,15 +80,133 @@ impl DatabaseConfig {
         &self.ssl_mode
     }
 
+    pub const fn get_run_migrations(&self) -> bool {
+        self.run_migrations
+    }
+
+    pub const fn get_connect_max_retries(&self) -> u32 {
+        self.connect_max_retries
+    }
+
+    pub const fn get_connect_retry_base_delay_ms(&self) -> u64 {
+        self.connect_retry_base_delay_ms
+    }
+
+    pub fn get_socket_path(&self) -> Option<&str> {
+        self.socket_path.as_deref()
+    }
+
+    pub const fn get_idle_timeout_secs(&self) -> u64 {
+        self.idle_timeout_secs
+    }
+
+    pub const fn get_max_lifetime_secs(&self) -> u64 {
+        self.max_lifetime_secs
+    }
+
+    pub const fn get_target_session_attrs(&self) -> &TargetSessionAttrs {
+        &self.target_session_attrs
+    }
+
+    pub const fn get_max_connections(&self) -> &u32 {
+        &self.max_connections
+    }
+
+    pub const fn get_min_connections(&self) -> &u32 {
+        &self.min_connections
+    }
+
+    pub const fn get_acquire_timeout_secs(&self) -> &u64 {
+        &self.acquire_timeout_secs
+    }
+
+    pub const fn get_pool_size_cpu_multiplier(&self) -> u32 {
+        self.pool_size_cpu_multiplier
+    }
+
+    pub fn get_ssl_root_cert(&self) -> Option<&PathBuf> {
+        self.ssl_root_cert.as_ref()
+    }
+
+    pub fn get_ssl_client_cert(&self) -> Option<&PathBuf> {
+        self.ssl_client_cert.as_ref()
+    }
+
+    pub fn get_ssl_client_key(&self) -> Option<&PathBuf> {
+        self.ssl_client_key.as_ref()
+    }
+
     /// Converts the DatabaseConfig to PgConnectOptions
     pub fn to_pg_connect_options(&self) -> PgConnectOptions {
-        PgConnectOptions::new()
+        let options = PgConnectOptions::new()
             .username(&self.username)
             .password(&self.password)
-            .port(self.port)
-            .host(&self.host)
-            .database(&self.name)
-            .ssl_mode(self.ssl_mode)
+            .database(&self.name);
+
+        match &self.socket_path {
+            // A Unix domain socket connection never negotiates TLS, so the
+            // certificate material below is only meaningful for TCP.
+            Some(socket_path) => options.socket(socket_path).ssl_mode(PgSslMode::Disable),
+            None => {
+                let options = options
+                    .host(&self.host)
+                    .port(self.port)
+                    .ssl_mode(self.ssl_mode);
+
+                let options = match &self.ssl_root_cert {
+                    Some(path) => options.ssl_root_cert(path),
+                    None => options,
+                };
+
+                let options = match &self.ssl_client_cert {
+                    Some(path) => options.ssl_client_cert(path),
+                    None => options,
+                };
+
+                match &self.ssl_client_key {
+                    Some(path) => options.ssl_client_key(path),
+                    None => options,
+                }
+            }
+        }
+    }
+}
+
+/// Which kind of Postgres node a connection is allowed to land on, used to
+/// steer the pool away from a standby during primary/replica failover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetSessionAttrs {
+    Any,
+    ReadWrite,
+}
+
+impl Default for TargetSessionAttrs {
+    fn default() -> Self {
+        Self::Any
+    }
+}
+
+impl TargetSessionAttrs {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::ReadWrite => "read-write",
+        }
+    }
+}
+
+impl FromStr for TargetSessionAttrs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Self::Any),
+            "read-write" => Ok(Self::ReadWrite),
+            other => Err(format!(
+                "invalid target_session_attrs value '{}', expected 'any' or 'read-write'",
+                other
+            )),
+        }
     }
 }
 
@@ -90,6 +236,20 @@ pub struct DatabaseConfigBuilder {
     host: Option<String>,
     name: Option<String>,
     ssl_mode: Option<PgSslMode>,
+    run_migrations: Option<bool>,
+    connect_max_retries: Option<u32>,
+    connect_retry_base_delay_ms: Option<u64>,
+    socket_path: Option<String>,
+    idle_timeout_secs: Option<u64>,
+    max_lifetime_secs: Option<u64>,
+    target_session_attrs: Option<TargetSessionAttrs>,
+    max_connections: Option<u32>,
+    min_connections: Option<u32>,
+    acquire_timeout_secs: Option<u64>,
+    pool_size_cpu_multiplier: Option<u32>,
+    ssl_root_cert: Option<PathBuf>,
+    ssl_client_cert: Option<PathBuf>,
+    ssl_client_key: Option<PathBuf>,
 }
 
 impl DatabaseConfigBuilder {
@@ -101,6 +261,20 @@ impl DatabaseConfigBuilder {
             host: None,
             name: None,
             ssl_mode: None,
+            run_migrations: None,
+            connect_max_retries: None,
+            connect_retry_base_delay_ms: None,
+            socket_path: None,
+            idle_timeout_secs: None,
+            max_lifetime_secs: None,
+            target_session_attrs: None,
+            max_connections: None,
+            min_connections: None,
+            acquire_timeout_secs: None,
+            pool_size_cpu_multiplier: None,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
         }
     }
 
@@ -133,6 +307,82 @@ impl DatabaseConfigBuilder {
         self.ssl_mode = Some(ssl_mode);
         self
     }
+
+    pub const fn with_run_migrations(mut self, run_migrations: bool) -> Self {
+        self.run_migrations = Some(run_migrations);
+        self
+    }
+
+    pub const fn with_connect_max_retries(mut self, connect_max_retries: u32) -> Self {
+        self.connect_max_retries = Some(connect_max_retries);
+        self
+    }
+
+    pub const fn with_connect_retry_base_delay_ms(
+        mut self,
+        connect_retry_base_delay_ms: u64,
+    ) -> Self {
+        self.connect_retry_base_delay_ms = Some(connect_retry_base_delay_ms);
+        self
+    }
+
+    pub fn with_socket_path(mut self, socket_path: impl Into<String>) -> Self {
+        self.socket_path = Some(socket_path.into());
+        self
+    }
+
+    pub const fn with_idle_timeout_secs(mut self, idle_timeout_secs: u64) -> Self {
+        self.idle_timeout_secs = Some(idle_timeout_secs);
+        self
+    }
+
+    pub const fn with_max_lifetime_secs(mut self, max_lifetime_secs: u64) -> Self {
+        self.max_lifetime_secs = Some(max_lifetime_secs);
+        self
+    }
+
+    pub const fn with_target_session_attrs(
+        mut self,
+        target_session_attrs: TargetSessionAttrs,
+    ) -> Self {
+        self.target_session_attrs = Some(target_session_attrs);
+        self
+    }
+
+    pub const fn with_max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    pub const fn with_min_connections(mut self, min_connections: u32) -> Self {
+        self.min_connections = Some(min_connections);
+        self
+    }
+
+    pub const fn with_acquire_timeout_secs(mut self, acquire_timeout_secs: u64) -> Self {
+        self.acquire_timeout_secs = Some(acquire_timeout_secs);
+        self
+    }
+
+    pub const fn with_pool_size_cpu_multiplier(mut self, pool_size_cpu_multiplier: u32) -> Self {
+        self.pool_size_cpu_multiplier = Some(pool_size_cpu_multiplier);
+        self
+    }
+
+    pub fn with_ssl_root_cert(mut self, ssl_root_cert: impl Into<PathBuf>) -> Self {
+        self.ssl_root_cert = Some(ssl_root_cert.into());
+        self
+    }
+
+    pub fn with_ssl_client_cert(mut self, ssl_client_cert: impl Into<PathBuf>) -> Self {
+        self.ssl_client_cert = Some(ssl_client_cert.into());
+        self
+    }
+
+    pub fn with_ssl_client_key(mut self, ssl_client_key: impl Into<PathBuf>) -> Self {
+        self.ssl_client_key = Some(ssl_client_key.into());
+        self
+    }
 }
 
 impl ConfigBuilder for DatabaseConfigBuilder {
@@ -218,6 +468,252 @@ impl ConfigBuilder for DatabaseConfigBuilder {
                     }
                 });
 
+        let run_migrations =
+            self.run_migrations
+                .unwrap_or_else(|| match read_env_var("DATABASE_RUN_MIGRATIONS") {
+                    Ok(r) => r.parse().unwrap_or_else(|e| {
+                        log::warn!(
+                            "{}. Using default {}",
+                            ConfigError::from_parse_bool_error("DATABASE_RUN_MIGRATIONS", e),
+                            DatabaseConfig::default().run_migrations
+                        );
+                        DatabaseConfig::default().run_migrations
+                    }),
+                    Err(e) => {
+                        log::warn!(
+                            "{}. Using default {}",
+                            e,
+                            DatabaseConfig::default().run_migrations
+                        );
+                        DatabaseConfig::default().run_migrations
+                    }
+                });
+
+        let connect_max_retries = self.connect_max_retries.unwrap_or_else(|| {
+            match read_env_var("DATABASE_CONNECT_MAX_RETRIES") {
+                Ok(r) => r.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_CONNECT_MAX_RETRIES", e),
+                        DatabaseConfig::default().connect_max_retries
+                    );
+                    DatabaseConfig::default().connect_max_retries
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().connect_max_retries
+                    );
+                    DatabaseConfig::default().connect_max_retries
+                }
+            }
+        });
+
+        let connect_retry_base_delay_ms = self.connect_retry_base_delay_ms.unwrap_or_else(|| {
+            match read_env_var("DATABASE_CONNECT_RETRY_BASE_DELAY_MS") {
+                Ok(d) => d.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error(
+                            "DATABASE_CONNECT_RETRY_BASE_DELAY_MS",
+                            e
+                        ),
+                        DatabaseConfig::default().connect_retry_base_delay_ms
+                    );
+                    DatabaseConfig::default().connect_retry_base_delay_ms
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().connect_retry_base_delay_ms
+                    );
+                    DatabaseConfig::default().connect_retry_base_delay_ms
+                }
+            }
+        });
+
+        let socket_path = self
+            .socket_path
+            .clone()
+            .or_else(|| read_env_var("DATABASE_SOCKET").ok());
+
+        let idle_timeout_secs = self.idle_timeout_secs.unwrap_or_else(|| {
+            match read_env_var("DATABASE_IDLE_TIMEOUT_SECS") {
+                Ok(t) => t.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_IDLE_TIMEOUT_SECS", e),
+                        DatabaseConfig::default().idle_timeout_secs
+                    );
+                    DatabaseConfig::default().idle_timeout_secs
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().idle_timeout_secs
+                    );
+                    DatabaseConfig::default().idle_timeout_secs
+                }
+            }
+        });
+
+        let max_lifetime_secs = self.max_lifetime_secs.unwrap_or_else(|| {
+            match read_env_var("DATABASE_MAX_LIFETIME_SECS") {
+                Ok(t) => t.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_MAX_LIFETIME_SECS", e),
+                        DatabaseConfig::default().max_lifetime_secs
+                    );
+                    DatabaseConfig::default().max_lifetime_secs
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().max_lifetime_secs
+                    );
+                    DatabaseConfig::default().max_lifetime_secs
+                }
+            }
+        });
+
+        let target_session_attrs = self
+            .target_session_attrs
+            .unwrap_or_else(|| match read_env_var("DATABASE_TARGET_SESSION_ATTRS") {
+                Ok(t) => TargetSessionAttrs::from_str(&t).unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().target_session_attrs.as_str()
+                    );
+                    DatabaseConfig::default().target_session_attrs
+                }),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    DatabaseConfig::default().target_session_attrs
+                }
+            });
+
+        let pool_size_cpu_multiplier = self.pool_size_cpu_multiplier.unwrap_or_else(|| {
+            match read_env_var("DATABASE_POOL_SIZE_CPU_MULTIPLIER") {
+                Ok(k) => k.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_POOL_SIZE_CPU_MULTIPLIER", e),
+                        DatabaseConfig::default().pool_size_cpu_multiplier
+                    );
+                    DatabaseConfig::default().pool_size_cpu_multiplier
+                }),
+                Err(_) => DatabaseConfig::default().pool_size_cpu_multiplier,
+            }
+        });
+
+        let max_connections = self.max_connections.unwrap_or_else(|| {
+            match read_env_var("DATABASE_MAX_CONNECTIONS") {
+                Ok(m) => m.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_MAX_CONNECTIONS", e),
+                        DatabaseConfig::default().max_connections
+                    );
+                    DatabaseConfig::default().max_connections
+                }),
+                // No explicit size configured: scale the pool to the host's logical
+                // CPU count instead of falling back to a hard-coded constant.
+                Err(_) => {
+                    let cpu_count = num_cpus::get() as u32;
+                    cpu_count * pool_size_cpu_multiplier + 1
+                }
+            }
+        });
+
+        let min_connections = self.min_connections.unwrap_or_else(|| {
+            match read_env_var("DATABASE_MIN_CONNECTIONS") {
+                Ok(m) => m.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_MIN_CONNECTIONS", e),
+                        DatabaseConfig::default().min_connections
+                    );
+                    DatabaseConfig::default().min_connections
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().min_connections
+                    );
+                    DatabaseConfig::default().min_connections
+                }
+            }
+        });
+
+        let acquire_timeout_secs = self
+            .acquire_timeout_secs
+            .unwrap_or_else(|| match read_env_var("DATABASE_ACQUIRE_TIMEOUT_SECS") {
+                Ok(t) => t.parse().unwrap_or_else(|e| {
+                    log::warn!(
+                        "{}. Using default {}",
+                        ConfigError::from_parse_int_error("DATABASE_ACQUIRE_TIMEOUT_SECS", e),
+                        DatabaseConfig::default().acquire_timeout_secs
+                    );
+                    DatabaseConfig::default().acquire_timeout_secs
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "{}. Using default {}",
+                        e,
+                        DatabaseConfig::default().acquire_timeout_secs
+                    );
+                    DatabaseConfig::default().acquire_timeout_secs
+                }
+            });
+
+        let ssl_root_cert = self.ssl_root_cert.clone().or_else(|| {
+            read_env_var("DATABASE_SSL_ROOT_CERT")
+                .ok()
+                .map(PathBuf::from)
+        });
+
+        let ssl_client_cert = self.ssl_client_cert.clone().or_else(|| {
+            read_env_var("DATABASE_SSL_CLIENT_CERT")
+                .ok()
+                .map(PathBuf::from)
+        });
+
+        let ssl_client_key = self.ssl_client_key.clone().or_else(|| {
+            read_env_var("DATABASE_SSL_CLIENT_KEY")
+                .ok()
+                .map(PathBuf::from)
+        });
+
+        // A Unix socket connection never negotiates TLS (see `to_pg_connect_options`),
+        // so a verifying `ssl_mode` left over from a TCP config shouldn't block boot
+        // on missing certificate files that will never actually be used.
+        if socket_path.is_none() && matches!(ssl_mode, PgSslMode::VerifyCa | PgSslMode::VerifyFull)
+        {
+            for (label, path) in [
+                ("DATABASE_SSL_ROOT_CERT", &ssl_root_cert),
+                ("DATABASE_SSL_CLIENT_CERT", &ssl_client_cert),
+                ("DATABASE_SSL_CLIENT_KEY", &ssl_client_key),
+            ] {
+                if let Some(path) = path {
+                    if !path.is_file() {
+                        log::error!(
+                            "{} points to '{}', which does not exist. Exiting...",
+                            label,
+                            path.display()
+                        );
+                        process::exit(1);
+                    }
+                }
+            }
+        }
+
         DatabaseConfig {
             username,
             password,
@@ -225,6 +721,20 @@ impl ConfigBuilder for DatabaseConfigBuilder {
             host,
             name,
             ssl_mode,
+            run_migrations,
+            connect_max_retries,
+            connect_retry_base_delay_ms,
+            socket_path,
+            idle_timeout_secs,
+            max_lifetime_secs,
+            target_session_attrs,
+            max_connections,
+            min_connections,
+            acquire_timeout_secs,
+            pool_size_cpu_multiplier,
+            ssl_root_cert,
+            ssl_client_cert,
+            ssl_client_key,
         }
     }
 }